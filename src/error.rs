@@ -4,15 +4,18 @@ use std::error::Error as StdError;
 use std::fmt::{self, Debug, Display};
 use std::mem;
 use std::ops::{Deref, DerefMut};
+use std::panic::Location;
 use std::ptr;
+use std::sync::OnceLock;
 
 /// The `Error` type, a wrapper around a dynamic error type.
 ///
 /// `Error` functions a lot like `Box<dyn std::error::Error>`, with these differences:
 ///
 /// - `Error` requires that the error is `Send`, `Sync`, and `'static`
-/// - `Error` guarantees that a backtrace will exist, even if the error type
-///   did not provide one
+/// - the default report handler captures a backtrace even if the error type did not
+///   provide one; a custom handler installed via [`set_hook`] may suppress it, in which
+///   case [`Error::backtrace`] returns `None`
 /// - `Error` is represented as a narrow pointer - exactly one word in size,
 ///   instead of two.
 pub struct Error {
@@ -26,6 +29,7 @@ impl Error {
     ///
     /// If the error type does not provide a backtrace, a backtrace will be created here to ensure
     /// that a backtrace exists.
+    #[track_caller]
     pub fn new<E>(error: E) -> Error
     where
         E: StdError + Send + Sync + 'static,
@@ -34,6 +38,7 @@ impl Error {
     }
 
     #[doc(hidden)]
+    #[track_caller]
     pub fn new_adhoc<M>(message: M) -> Error
     where
         M: Display + Debug + Send + Sync + 'static,
@@ -41,21 +46,22 @@ impl Error {
         Error::construct(MessageError(message), TypeId::of::<M>())
     }
 
+    #[track_caller]
     fn construct<E>(error: E, type_id: TypeId) -> Error
     where
         E: StdError + Send + Sync + 'static,
     {
+        let location = Location::caller();
         unsafe {
-            let backtrace = match error.backtrace() {
-                Some(_) => None,
-                None => Some(Backtrace::capture()),
-            };
+            let mut handler = capture_handler(&error);
+            handler.track_caller(location);
             let obj: TraitObject = mem::transmute(&error as &dyn StdError);
             let vtable = obj.vtable;
             let inner = ErrorImpl {
                 vtable,
                 type_id,
-                backtrace,
+                handler,
+                location,
                 error,
             };
             Error {
@@ -74,15 +80,42 @@ impl Error {
         &mut **self
     }
 
+    /// The source location at which this Error was created.
+    ///
+    /// Because `?`-propagated conversions go through [`From`], this reports the exact site of
+    /// each converted error, which a backtrace alone often loses once frames are inlined in
+    /// release builds.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.inner.location
+    }
+
+    /// Request a reference to typed side-data attached to this error or its cause chain.
+    ///
+    /// Walks the cause chain and returns the first `&T` offered by a link through its
+    /// [`StdError::provide`] hook. This lets a handler pull out, say, an HTTP status code or a
+    /// span trace by type without downcasting to a concrete error enum.
+    pub fn request_ref<T: ?Sized + 'static>(&self) -> Option<&T> {
+        self.errors().find_map(|error| std::error::request_ref::<T>(error))
+    }
+
+    /// Request an owned value of typed side-data attached to this error or its cause chain.
+    ///
+    /// Walks the cause chain and returns the first `T` offered by a link through its
+    /// [`StdError::provide`] hook.
+    pub fn request_value<T: 'static>(&self) -> Option<T> {
+        self.errors().find_map(|error| std::error::request_value::<T>(error))
+    }
+
     /// Get the backtrace for this Error.
-    pub fn backtrace(&self) -> &Backtrace {
-        // NB: this unwrap can only fail if the underlying error's backtrace method is
-        // nondeterministic, which would only happen in maliciously constructed code
+    ///
+    /// The default report handler always captures one, but a custom handler installed via
+    /// [`set_hook`] may decline to, in which case this returns `None` (falling back to a
+    /// backtrace carried by the underlying error, if any).
+    pub fn backtrace(&self) -> Option<&Backtrace> {
         self.inner
-            .backtrace
-            .as_ref()
+            .handler
+            .backtrace()
             .or_else(|| self.inner.error().backtrace())
-            .expect("exception backtrace capture failed")
     }
 
     /// An iterator of errors contained by this Error.
@@ -134,6 +167,7 @@ impl Error {
 }
 
 impl<E: StdError + Send + Sync + 'static> From<E> for Error {
+    #[track_caller]
     fn from(error: E) -> Error {
         Error::new(error)
     }
@@ -154,41 +188,13 @@ impl DerefMut for Error {
 
 impl Debug for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "{}", self.inner.error())?;
-
-        let mut errors = self.errors().skip(1).enumerate();
-
-        if let Some((n, error)) = errors.next() {
-            writeln!(f, "\ncaused by:")?;
-            writeln!(f, "\t{}: {}", n, error)?;
-            for (n, error) in errors {
-                writeln!(f, "\t{}: {}", n, error)?;
-            }
-        }
-
-        let backtrace = self.backtrace();
-
-        match backtrace.status() {
-            BacktraceStatus::Captured => {
-                writeln!(f, "\n{}", backtrace)?;
-            }
-            BacktraceStatus::Disabled => {
-                writeln!(
-                    f,
-                    "\nbacktrace disabled; run with RUST_BACKTRACE=1 environment variable \
-                     to display a backtrace"
-                )?;
-            }
-            _ => {}
-        }
-
-        Ok(())
+        self.inner.handler.debug(self.inner.error(), f)
     }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.inner.error())
+        self.inner.handler.display(self.inner.error(), f)
     }
 }
 
@@ -201,12 +207,70 @@ impl Drop for Error {
     }
 }
 
+/// A handler owning how an [`Error`] is rendered and what auxiliary data it captures.
+///
+/// This plays the same role as the formatting logic that used to be hardcoded in the `Debug`
+/// and `Display` impls: the default handler reproduces that behavior, printing the "caused by"
+/// chain followed by the captured backtrace. Downstream applications can install their own via
+/// [`set_hook`] to add colorization, span traces, or to suppress the backtrace entirely, without
+/// forking the crate.
+pub trait ReportHandler: Send + Sync + 'static {
+    /// Format the error and its cause chain for the `Debug` representation of [`Error`].
+    fn debug(&self, error: &(dyn StdError + 'static), f: &mut fmt::Formatter) -> fmt::Result;
+
+    /// Format the error for the `Display` representation of [`Error`].
+    ///
+    /// Defaults to the `Display` of the underlying error.
+    fn display(&self, error: &(dyn StdError + 'static), f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", error)
+    }
+
+    /// The backtrace captured by this handler, if any.
+    ///
+    /// Returned by [`Error::backtrace`] in preference to a backtrace provided by the underlying
+    /// error. Handlers that do not capture a backtrace leave this as `None`.
+    fn backtrace(&self) -> Option<&Backtrace> {
+        None
+    }
+
+    /// Record the source location at which the error was created.
+    ///
+    /// Called once during construction. The default handler prints it above the backtrace;
+    /// other handlers may record or ignore it.
+    fn track_caller(&mut self, _location: &'static Location<'static>) {}
+}
+
+type Hook = Box<dyn Fn(&(dyn StdError + 'static)) -> Box<dyn ReportHandler> + Send + Sync>;
+
+static HOOK: OnceLock<Hook> = OnceLock::new();
+
+/// Install a global hook that builds the [`ReportHandler`] for every [`Error`] as it is created.
+///
+/// The hook replaces the default handler, which reproduces the built-in "caused by" plus
+/// backtrace rendering. It should be installed once, early in a program's lifetime; a later call
+/// after the hook has already been set has no effect.
+pub fn set_hook(hook: Hook) {
+    let _ = HOOK.set(hook);
+}
+
+fn capture_handler(error: &(dyn StdError + 'static)) -> Box<dyn ReportHandler> {
+    match HOOK.get() {
+        Some(hook) => hook(error),
+        None => Box::new(DefaultHandler {
+            backtrace: OnceLock::new(),
+            has_own_backtrace: error.backtrace().is_some(),
+            location: None,
+        }),
+    }
+}
+
 // repr C to ensure that `E` remains in the final position
 #[repr(C)]
 struct ErrorImpl<E> {
     vtable: *const (),
     type_id: TypeId,
-    backtrace: Option<Backtrace>,
+    handler: Box<dyn ReportHandler>,
+    location: &'static Location<'static>,
     error: E,
 }
 
@@ -234,6 +298,216 @@ impl<M: Display + Debug> Display for MessageError<M> {
 
 impl<M: Display + Debug + 'static> StdError for MessageError<M> {}
 
+/// Attach human-readable context to the error in a `Result` or the absence in an `Option`.
+///
+/// The context line is rendered on top of the original cause chain: the wrapper error's
+/// [`source`](StdError::source) returns the inner error, so the existing [`Errors`] iterator and
+/// the `Debug` "caused by" layout pick it up with no special handling.
+///
+/// [`source`]: StdError::source
+pub trait Context<T> {
+    /// Wrap the error with a context value.
+    fn context<C>(self, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static;
+
+    /// Wrap the error with a context value computed lazily, only on the error path.
+    fn with_context<C, F>(self, f: F) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C;
+}
+
+impl<T, E> Context<T> for Result<T, E>
+where
+    E: StdError + Send + Sync + 'static,
+{
+    fn context<C>(self, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        self.map_err(|error| Error::new(ContextError { context, error }))
+    }
+
+    fn with_context<C, F>(self, f: F) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        self.map_err(|error| Error::new(ContextError { context: f(), error }))
+    }
+}
+
+impl<T> Context<T> for Result<T, Error> {
+    fn context<C>(self, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        self.map_err(|error| Error::new(ContextError { context, error }))
+    }
+
+    fn with_context<C, F>(self, f: F) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        self.map_err(|error| Error::new(ContextError { context: f(), error }))
+    }
+}
+
+impl<T> Context<T> for Option<T> {
+    fn context<C>(self, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        self.ok_or_else(|| Error::new(DisplayError(context)))
+    }
+
+    fn with_context<C, F>(self, f: F) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        self.ok_or_else(|| Error::new(DisplayError(f())))
+    }
+}
+
+/// A context value attached on top of an underlying error.
+struct ContextError<C, E> {
+    context: C,
+    error: E,
+}
+
+impl<C: Display, E: Debug> Debug for ContextError<C, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ContextError")
+            .field("context", &format_args!("{}", self.context))
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+impl<C: Display, E> Display for ContextError<C, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.context, f)
+    }
+}
+
+impl<C: Display, E: StdError + 'static> StdError for ContextError<C, E> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.error)
+    }
+}
+
+// When context is attached to an already-constructed `Error`, the original error is not a
+// `StdError`; report its underlying error as the source and forward its backtrace so that
+// `construct` finds one and does not capture a fresh (and less useful) one here.
+impl<C: Display> StdError for ContextError<C, Error> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self.error.as_error())
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.error.backtrace()
+    }
+}
+
+/// Wraps a `Display`-only value so it can be used as the error in an [`Error`].
+///
+/// Used for the [`Option`] implementation of [`Context`], where there is no underlying error and
+/// the context itself becomes the error's message. Its `Debug` output mirrors its `Display`.
+#[repr(transparent)]
+struct DisplayError<C>(C);
+
+impl<C: Display> Debug for DisplayError<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl<C: Display> Display for DisplayError<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl<C: Display + 'static> StdError for DisplayError<C> {}
+
+/// The default [`ReportHandler`], reproducing the crate's built-in error rendering.
+///
+/// The backtrace is captured lazily: if the underlying error does not already carry one, it is
+/// captured on the first call to [`Error::backtrace`] (or when the error is formatted with `Debug`)
+/// rather than during construction, so hot error paths that are never inspected pay nothing.
+struct DefaultHandler {
+    backtrace: OnceLock<Backtrace>,
+    has_own_backtrace: bool,
+    location: Option<&'static Location<'static>>,
+}
+
+impl DefaultHandler {
+    /// The captured backtrace, capturing on first access unless the underlying error provides
+    /// its own (in which case this returns `None` and the caller falls back to that one).
+    fn backtrace(&self) -> Option<&Backtrace> {
+        if self.has_own_backtrace {
+            None
+        } else {
+            Some(self.backtrace.get_or_init(Backtrace::capture))
+        }
+    }
+}
+
+impl ReportHandler for DefaultHandler {
+    fn debug(&self, error: &(dyn StdError + 'static), f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", error)?;
+
+        let mut errors = Errors { next: Some(error) }.skip(1).enumerate();
+
+        if let Some((n, error)) = errors.next() {
+            writeln!(f, "\ncaused by:")?;
+            writeln!(f, "\t{}: {}", n, error)?;
+            for (n, error) in errors {
+                writeln!(f, "\t{}: {}", n, error)?;
+            }
+        }
+
+        if let Some(location) = self.location {
+            writeln!(
+                f,
+                "\nat {}:{}:{}",
+                location.file(),
+                location.line(),
+                location.column()
+            )?;
+        }
+
+        if let Some(backtrace) = self.backtrace().or_else(|| error.backtrace()) {
+            match backtrace.status() {
+                BacktraceStatus::Captured => {
+                    writeln!(f, "\n{}", backtrace)?;
+                }
+                BacktraceStatus::Disabled => {
+                    writeln!(
+                        f,
+                        "\nbacktrace disabled; run with RUST_BACKTRACE=1 environment variable \
+                         to display a backtrace"
+                    )?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        DefaultHandler::backtrace(self)
+    }
+
+    fn track_caller(&mut self, location: &'static Location<'static>) {
+        self.location = Some(location);
+    }
+}
+
 impl ErrorImpl<()> {
     fn error(&self) -> &(dyn StdError + Send + Sync + 'static) {
         unsafe {
@@ -315,3 +589,96 @@ mod repr_correctness {
         assert!(*has_dropped.lock().unwrap());
     }
 }
+
+#[cfg(test)]
+mod behavior {
+    use super::*;
+
+    #[derive(Debug)]
+    struct StatusCode(u16);
+
+    #[derive(Debug)]
+    struct ApiError {
+        code: StatusCode,
+    }
+
+    impl Display for ApiError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "request failed")
+        }
+    }
+
+    impl StdError for ApiError {
+        fn provide<'a>(&'a self, request: &mut std::error::Request<'a>) {
+            request
+                .provide_ref::<StatusCode>(&self.code)
+                .provide_value::<u16>(self.code.0);
+        }
+    }
+
+    #[test]
+    fn request_ref_round_trip() {
+        let error = Error::new(ApiError {
+            code: StatusCode(404),
+        });
+        assert_eq!(error.request_ref::<StatusCode>().map(|s| s.0), Some(404));
+        assert_eq!(error.request_value::<u16>(), Some(404));
+    }
+
+    #[test]
+    fn request_ref_walks_cause_chain() {
+        let error: Error = Err::<(), _>(ApiError {
+            code: StatusCode(500),
+        })
+        .context("while fetching the widget")
+        .unwrap_err();
+
+        // The status code lives on a link below the context wrapper.
+        assert_eq!(error.request_ref::<StatusCode>().map(|s| s.0), Some(500));
+    }
+
+    #[test]
+    fn location_reports_creation_site() {
+        let expected_line = line!() + 1;
+        let error = Error::new(ApiError {
+            code: StatusCode(400),
+        });
+
+        let location = error.location();
+        assert!(location.file().ends_with("error.rs"));
+        assert_eq!(location.line(), expected_line);
+    }
+
+    #[test]
+    fn location_reports_question_mark_site() {
+        // `?` converts through `From`, which is `#[track_caller]`, so the recorded location is
+        // the `?` expression two lines below.
+        let question_line = line!() + 2;
+        let result: Result<(), Error> = (|| {
+            Err(ApiError { code: StatusCode(418) })?;
+            Ok(())
+        })();
+
+        let error = result.unwrap_err();
+        assert_eq!(error.location().line(), question_line);
+    }
+
+    #[test]
+    fn context_preserves_original_backtrace() {
+        let base = Error::new(ApiError {
+            code: StatusCode(503),
+        });
+        // Force the lazy backtrace to be materialized, then remember its address.
+        let base_backtrace = base.backtrace().map(|bt| bt as *const Backtrace);
+
+        let wrapped: Error = Err::<(), _>(base)
+            .context("while talking to the backend")
+            .unwrap_err();
+
+        // The wrapped error reports the very same backtrace, not a freshly captured one.
+        assert_eq!(
+            wrapped.backtrace().map(|bt| bt as *const Backtrace),
+            base_backtrace
+        );
+    }
+}